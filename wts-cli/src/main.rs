@@ -2,9 +2,11 @@ use candle::safetensors::load;
 use clap::{Parser, Subcommand};
 use std::{
     fs::File,
-    io::Read,
-    path::{Path, PathBuf},
+    io::{BufWriter, Read},
+    path::PathBuf,
 };
+use wts_core::diff::TensorDiff;
+use wts_core::sign::Identity;
 use wts_core::{Repository, WTSError};
 
 #[derive(Parser)]
@@ -21,6 +23,9 @@ enum Commands {
         /// Optional path to initialize the repository
         #[arg(default_value = ".")]
         path: PathBuf,
+        /// Encrypt everything written under .wts/objects and .wts/commits
+        #[arg(long)]
+        encrypt: bool,
     },
     /// Create a new commit
     Commit {
@@ -33,6 +38,13 @@ enum Commands {
         /// Optional metadata as JSON string
         #[arg(short = 'd', long)]
         metadata: Option<String>,
+        /// Author name; when given with --author-email, the commit is
+        /// signed with the repo's Ed25519 signing key
+        #[arg(long)]
+        author_name: Option<String>,
+        /// Author email; see --author-name
+        #[arg(long)]
+        author_email: Option<String>,
     },
     /// Create a new branch
     Branch {
@@ -54,25 +66,71 @@ enum Commands {
     Show {
         /// Commit hash to show
         hash: String,
+        /// Walk the history from this commit and report signature validity
+        #[arg(long)]
+        verify: bool,
     },
     CatFile {
         hash: String,
     },
+    /// Delete chunks no longer reachable from any branch or tag
+    Gc,
+    /// Compare the tensors committed at two commits
+    Diff {
+        /// Commit hash to diff from
+        from: String,
+        /// Commit hash to diff to
+        to: String,
+        /// Print the diff as JSON instead of a human-readable table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Pack the history reachable from one or more refs into a single
+    /// file for offline transfer
+    Bundle {
+        /// Branch names, tag names, or commit hashes to include
+        refs: Vec<String>,
+        /// Path to write the bundle to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Import commits, objects, and chunks from a bundle file
+    Unbundle {
+        /// Path to the bundle file
+        file: PathBuf,
+    },
+    /// Stream commit history from the commit index
+    Log {
+        /// Commit hash to start from
+        hash: String,
+        /// "text" (hash + timestamp only, no disk reads beyond the index)
+        /// or "json" (full commit record per entry, for interop)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+}
+
+/// Passphrase for an encrypted repository, read from `WTS_PASSPHRASE` so
+/// it never has to be typed into shell history.
+fn passphrase() -> Option<String> {
+    std::env::var("WTS_PASSPHRASE").ok()
 }
 
 fn main() -> Result<(), WTSError> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Init { path } => {
+        Commands::Init { path, encrypt } => {
             let repo = Repository::new(path)?;
-            repo.init()?;
+            repo.init_with_encryption(*encrypt)?;
             println!("Initialized empty WST repository at {:?}", path);
         }
         Commands::Commit {
             file,
             message,
             metadata,
+            author_name,
+            author_email,
         } => {
             let repo = Repository::open()?;
 
@@ -106,7 +164,26 @@ fn main() -> Result<(), WTSError> {
                 serde_json::Value::Null
             };
 
-            let hash = repo.create_commit(&tensors, message, metadata_value, None)?;
+            let hash = match (author_name, author_email) {
+                (Some(name), Some(email)) => repo.create_signed_commit(
+                    &tensors,
+                    message,
+                    metadata_value,
+                    None,
+                    Identity {
+                        name: name.clone(),
+                        email: email.clone(),
+                    },
+                    passphrase().as_deref(),
+                )?,
+                _ => repo.create_commit(
+                    &tensors,
+                    message,
+                    metadata_value,
+                    None,
+                    passphrase().as_deref(),
+                )?,
+            };
             println!("Created commit: {}", hash);
         }
         Commands::Branch { name, commit } => {
@@ -119,19 +196,112 @@ fn main() -> Result<(), WTSError> {
             repo.create_tag(name, commit)?;
             println!("Created tag '{}' at {}", name, commit);
         }
-        Commands::Show { hash } => {
+        Commands::Show { hash, verify } => {
             let repo = Repository::new(".")?;
-            let commit = repo.get_commit(hash)?;
+            let commit = repo.get_commit_with_passphrase(hash, passphrase().as_deref())?;
             println!("{:#?}", commit);
+
+            if *verify {
+                for (commit_hash, valid) in repo.verify_history(hash, passphrase().as_deref())? {
+                    let status = if valid { "valid" } else { "invalid/unsigned" };
+                    println!("{commit_hash}: {status}");
+                }
+            }
         }
         Commands::CatFile { hash } => {
             let repo = Repository::open()?;
 
-            let path = Path::new(".wts").join("objects").join(hash);
-            let obj = repo.get_obj(path.to_str().unwrap(), &candle::Device::Cpu)?;
+            let obj = repo.get_obj_with_passphrase(
+                hash,
+                &candle::Device::Cpu,
+                passphrase().as_deref(),
+            )?;
 
             println!("{obj:?}");
         }
+        Commands::Gc => {
+            let repo = Repository::open()?;
+            let report = repo.gc(passphrase().as_deref())?;
+            println!(
+                "Deleted {} unreachable chunk(s), freed {} bytes",
+                report.chunks_deleted, report.bytes_freed
+            );
+        }
+        Commands::Diff { from, to, json } => {
+            let repo = Repository::open()?;
+            let report = repo.diff(from, to, &candle::Device::Cpu, passphrase().as_deref())?;
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            } else {
+                let mut names: Vec<&String> = report.tensors.keys().collect();
+                names.sort();
+
+                for name in names {
+                    match &report.tensors[name] {
+                        TensorDiff::Added { shape, dtype } => {
+                            println!("+ {name}  {dtype} {shape:?}");
+                        }
+                        TensorDiff::Removed { shape, dtype } => {
+                            println!("- {name}  {dtype} {shape:?}");
+                        }
+                        TensorDiff::Modified {
+                            from_shape,
+                            to_shape,
+                            from_dtype,
+                            to_dtype,
+                            stats,
+                        } => {
+                            print!("~ {name}  {from_dtype} {from_shape:?} -> {to_dtype} {to_shape:?}");
+                            match stats {
+                                Some(stats) => println!(
+                                    "  (l2={:.6} max_abs={:.6} mean_abs={:.6} cos_sim={:.6})",
+                                    stats.l2_norm,
+                                    stats.max_abs_diff,
+                                    stats.mean_abs_diff,
+                                    stats.cosine_similarity
+                                ),
+                                None => println!(),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Bundle { refs, output } => {
+            let repo = Repository::open()?;
+            let ref_names: Vec<&str> = refs.iter().map(String::as_str).collect();
+            let file = File::create(output)?;
+            repo.create_bundle(&ref_names, BufWriter::new(file))?;
+            println!("Wrote bundle to {:?}", output);
+        }
+        Commands::Unbundle { file } => {
+            let repo = Repository::open()?;
+            let reader = File::open(file)?;
+            let refs = repo.import_bundle(reader)?;
+            for (name, commit_hash) in refs {
+                println!("{name}: {commit_hash}");
+            }
+        }
+        Commands::Log { hash, format } => {
+            let repo = Repository::open()?;
+            let entries = repo.log(hash)?;
+
+            if format == "json" {
+                for (hex_hash, _) in &entries {
+                    let commit = repo.get_commit_with_passphrase(hex_hash, passphrase().as_deref())?;
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&commit)
+                            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?
+                    );
+                }
+            } else {
+                for (hex_hash, entry) in &entries {
+                    println!("{hex_hash}  {}", entry.timestamp.0);
+                }
+            }
+        }
     }
 
     Ok(())
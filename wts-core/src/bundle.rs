@@ -0,0 +1,365 @@
+//! Self-contained bundles for offline commit transfer.
+//!
+//! A bundle packs a set of commits plus every object and chunk they
+//! reach into a single stream: a length-prefixed JSON header (format
+//! version, the refs included, and every commit hash reachable from
+//! them) followed by length-prefixed `(key, bytes)` records. Moving a
+//! model's history between repos then only takes copying one file, no
+//! live connection to the source required.
+//!
+//! Every record is checked before it's trusted. Chunk records are
+//! verified against their content hash directly (they're the only
+//! objects in this store that are truly content addressed); this only
+//! holds for unencrypted repositories, since an encrypted chunk's
+//! on-disk bytes no longer hash to its plaintext key. Commit records are
+//! checked by decoding them and confirming the commit's own `hash` field
+//! matches the key it was filed under in the bundle. Object (tensor
+//! manifest) records are held back until every chunk they reference has
+//! itself been verified, so a manifest can't be imported pointing at
+//! chunks that were never actually checked. As with chunk verification,
+//! object-manifest verification only applies to unencrypted repositories
+//! — an encrypted manifest is opaque ciphertext, the same limitation
+//! imported commits already carry: rebuilding the local commit index
+//! requires decoding the commit record to recover its parent hash, so
+//! bundles of encrypted commits can't be indexed without the source
+//! repo's passphrase.
+
+use sha2::{Digest, Sha512};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use crate::chunk::chunk_key as store_chunk_key;
+use crate::{object_key, ObjectManifest, Repository, WTSError};
+
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+/// Largest `key`/header length a record's length prefix may claim. Bundles
+/// are read from untrusted sources (a peer's push, a downloaded file), so
+/// these bound how much memory a single corrupt or malicious length
+/// prefix can make `import_bundle` allocate before the real content even
+/// gets a chance to fail its integrity check.
+const MAX_KEY_LEN: usize = 4 * 1024;
+const MAX_PAYLOAD_LEN: usize = 1024 * 1024 * 1024;
+const MAX_HEADER_LEN: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct BundleHeader {
+    version: u32,
+    /// `(ref_name, commit_hex_hash)` pairs the bundle was created from.
+    refs: Vec<(String, String)>,
+    /// Every commit hash reachable from `refs`, included in the bundle.
+    commits: Vec<String>,
+    /// Number of `(key, bytes)` records that follow the header.
+    record_count: u32,
+}
+
+fn write_record(out: &mut impl Write, key: &str, payload: &[u8]) -> Result<(), WTSError> {
+    let key_bytes = key.as_bytes();
+    out.write_all(&(key_bytes.len() as u32).to_le_bytes())?;
+    out.write_all(key_bytes)?;
+    out.write_all(&(payload.len() as u64).to_le_bytes())?;
+    out.write_all(payload)?;
+    Ok(())
+}
+
+fn read_exact_vec(input: &mut impl Read, len: usize, max_len: usize) -> Result<Vec<u8>, WTSError> {
+    if len > max_len {
+        return Err(WTSError::Other(format!(
+            "bundle record claims {len} bytes, exceeding the {max_len}-byte limit"
+        )));
+    }
+    let mut buf = vec![0u8; len];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_record(input: &mut impl Read) -> Result<(String, Vec<u8>), WTSError> {
+    let mut len_buf = [0u8; 4];
+    input.read_exact(&mut len_buf)?;
+    let key_len = u32::from_le_bytes(len_buf) as usize;
+    let key = String::from_utf8(read_exact_vec(input, key_len, MAX_KEY_LEN)?)
+        .map_err(|e| WTSError::Other(e.to_string()))?;
+
+    let mut payload_len_buf = [0u8; 8];
+    input.read_exact(&mut payload_len_buf)?;
+    let payload_len = u64::from_le_bytes(payload_len_buf) as usize;
+    let payload = read_exact_vec(input, payload_len, MAX_PAYLOAD_LEN)?;
+
+    Ok((key, payload))
+}
+
+fn chunk_key(hash: &str) -> String {
+    format!("chunks/{hash}")
+}
+
+fn commit_record_key(hash: &str) -> String {
+    format!("commits/{hash}")
+}
+
+impl Repository {
+    /// Resolve `refs` (branch names, tag names, or raw commit hashes, in
+    /// that order of precedence) and write a bundle containing every
+    /// commit reachable from them plus their tensor objects and chunks.
+    pub fn create_bundle(&self, refs: &[&str], mut out: impl Write) -> Result<(), WTSError> {
+        let mut resolved_refs = Vec::new();
+        let mut commit_hashes: Vec<Vec<u8>> = Vec::new();
+
+        for &name in refs {
+            let commit_hex = self
+                .get_reference(&format!("refs/heads/{name}"))
+                .or_else(|_| self.get_reference(&format!("refs/tags/{name}")))
+                .unwrap_or_else(|_| name.to_string());
+            resolved_refs.push((name.to_string(), commit_hex.clone()));
+            commit_hashes.push(hex::decode(&commit_hex).map_err(|e| WTSError::Other(e.to_string()))?);
+        }
+
+        let mut seen_commits = HashSet::new();
+        let mut commit_order = Vec::new();
+        let mut chunk_hashes: HashSet<String> = HashSet::new();
+
+        for start in commit_hashes {
+            let iter = crate::CommitIterator {
+                repo: self,
+                current_hash: Some(start),
+                cipher: None,
+            };
+
+            for commit in iter {
+                let commit = commit?;
+                let hex_hash = hex::encode(&commit.hash);
+                if !seen_commits.insert(hex_hash.clone()) {
+                    continue;
+                }
+
+                let manifest_bytes = self.store.get(&object_key(&hex_hash))?;
+                let manifest: ObjectManifest = serde_json::from_str(
+                    std::str::from_utf8(&manifest_bytes).map_err(|e| WTSError::Other(e.to_string()))?,
+                )
+                .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+                for entry in manifest.tensors.values() {
+                    chunk_hashes.extend(entry.chunks.iter().cloned());
+                }
+
+                commit_order.push(hex_hash);
+            }
+        }
+
+        let header = BundleHeader {
+            version: BUNDLE_FORMAT_VERSION,
+            refs: resolved_refs,
+            commits: commit_order.clone(),
+            record_count: (commit_order.len() * 2 + chunk_hashes.len()) as u32,
+        };
+        let header_json =
+            serde_json::to_vec(&header).map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+        out.write_all(&(header_json.len() as u64).to_le_bytes())?;
+        out.write_all(&header_json)?;
+
+        for hex_hash in &commit_order {
+            let commit_bytes = self.raw_commit_bytes(hex_hash)?;
+            write_record(&mut out, &commit_record_key(hex_hash), &commit_bytes)?;
+
+            let object_bytes = self.store.get(&object_key(hex_hash))?;
+            write_record(&mut out, &object_key(hex_hash), &object_bytes)?;
+        }
+
+        for chunk_hash in &chunk_hashes {
+            let chunk_bytes = self
+                .store
+                .get(&store_chunk_key(chunk_hash))
+                .map_err(|_| WTSError::ObjectNotFound(format!("chunk {chunk_hash}")))?;
+            write_record(&mut out, &chunk_key(chunk_hash), &chunk_bytes)?;
+        }
+
+        Ok(())
+    }
+
+    /// Import every commit, object, and chunk from a bundle produced by
+    /// [`Repository::create_bundle`], skipping anything already present
+    /// and verifying chunk payloads against their claimed content hash.
+    /// Returns the `(ref_name, commit_hex_hash)` pairs the bundle carried.
+    pub fn import_bundle(&self, mut input: impl Read) -> Result<Vec<(String, String)>, WTSError> {
+        let mut len_buf = [0u8; 8];
+        input.read_exact(&mut len_buf)?;
+        let header_len = u64::from_le_bytes(len_buf) as usize;
+        let header_bytes = read_exact_vec(&mut input, header_len, MAX_HEADER_LEN)?;
+        let header: BundleHeader = serde_json::from_slice(&header_bytes)
+            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+
+        if header.version != BUNDLE_FORMAT_VERSION {
+            return Err(WTSError::Other(format!(
+                "unsupported bundle format version {}",
+                header.version
+            )));
+        }
+
+        let mut verified_chunks: HashSet<String> = HashSet::new();
+        let mut pending_objects: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut written: HashSet<String> = HashSet::new();
+
+        for _ in 0..header.record_count {
+            let (key, payload) = read_record(&mut input)?;
+
+            if let Some(chunk_hash) = key.strip_prefix("chunks/") {
+                let store_key = store_chunk_key(chunk_hash);
+                if self.store.exists(&store_key)? {
+                    verified_chunks.insert(chunk_hash.to_string());
+                    continue;
+                }
+
+                let mut hasher = Sha512::new();
+                hasher.update(&payload);
+                let actual_hash = hex::encode(hasher.finalize());
+                if actual_hash != chunk_hash {
+                    return Err(WTSError::Other(format!(
+                        "chunk {chunk_hash} failed integrity check (bundle may be encrypted or corrupt)"
+                    )));
+                }
+
+                self.store.put(&store_key, &payload)?;
+                verified_chunks.insert(chunk_hash.to_string());
+                continue;
+            }
+
+            if let Some(hex_hash) = key.strip_prefix("commits/") {
+                if self.has_commit_record(hex_hash)? {
+                    continue;
+                }
+                self.import_commit_record(hex_hash, payload)?;
+                continue;
+            }
+
+            if key.starts_with("objects/") {
+                pending_objects.push((key, payload));
+                continue;
+            }
+
+            return Err(WTSError::Other(format!(
+                "bundle record {key} has an unrecognized key (expected a chunks/, commits/, or objects/ prefix)"
+            )));
+        }
+
+        let encrypted = self.read_config()?.encrypted;
+        for (key, payload) in pending_objects {
+            if written.contains(&key) || self.store.exists(&key)? {
+                continue;
+            }
+
+            // An encrypted manifest is opaque ciphertext we can't inspect
+            // without the repo's passphrase; chunk verification has the
+            // same limitation (see the module doc comment).
+            if !encrypted {
+                let manifest_json = std::str::from_utf8(&payload)
+                    .map_err(|e| WTSError::Other(format!("object {key} is not valid UTF-8: {e}")))?;
+                let manifest: ObjectManifest = serde_json::from_str(manifest_json).map_err(|_| {
+                    WTSError::Other(format!(
+                        "object {key} failed integrity check: not a valid tensor manifest"
+                    ))
+                })?;
+                for entry in manifest.tensors.values() {
+                    for chunk_hash in &entry.chunks {
+                        if !verified_chunks.contains(chunk_hash) {
+                            return Err(WTSError::Other(format!(
+                                "object {key} references chunk {chunk_hash}, which was never verified in this bundle"
+                            )));
+                        }
+                    }
+                }
+            }
+
+            self.store.put(&key, &payload)?;
+            written.insert(key);
+        }
+
+        Ok(header.refs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::LocalFsStore;
+    use candle::{Device, Tensor};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn test_repo(name: &str) -> (Repository, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wts-bundle-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::with_store(&dir, Box::new(LocalFsStore::new(dir.clone())));
+        (repo, dir)
+    }
+
+    fn one_tensor() -> HashMap<String, Tensor> {
+        let mut tensors = HashMap::new();
+        tensors.insert(
+            "weight".to_string(),
+            Tensor::new(&[1.0f32, 2.0, 3.0], &Device::Cpu).unwrap(),
+        );
+        tensors
+    }
+
+    #[test]
+    fn create_then_import_round_trips_commit_and_tensors() {
+        let (source, source_dir) = test_repo("roundtrip-source");
+        let (dest, dest_dir) = test_repo("roundtrip-dest");
+
+        let hash = source
+            .create_commit(&one_tensor(), "initial commit", json!({"k": "v"}), None, None)
+            .unwrap();
+        source.create_branch("main", &hash).unwrap();
+
+        let mut buf = Vec::new();
+        source.create_bundle(&["main"], &mut buf).unwrap();
+
+        let refs = dest.import_bundle(&buf[..]).unwrap();
+        assert_eq!(refs, vec![("main".to_string(), hash.clone())]);
+
+        let commit = dest.get_commit(&hash).unwrap();
+        assert_eq!(commit.message, "initial commit");
+
+        let tensors = dest.get_obj(&hash, &Device::Cpu).unwrap();
+        assert_eq!(tensors["weight"].to_vec1::<f32>().unwrap(), vec![1.0, 2.0, 3.0]);
+
+        std::fs::remove_dir_all(&source_dir).ok();
+        std::fs::remove_dir_all(&dest_dir).ok();
+    }
+
+    #[test]
+    fn import_rejects_tampered_chunk() {
+        let (source, source_dir) = test_repo("tamper-source");
+        let (dest, dest_dir) = test_repo("tamper-dest");
+
+        let hash = source
+            .create_commit(&one_tensor(), "initial commit", json!({}), None, None)
+            .unwrap();
+        source.create_branch("main", &hash).unwrap();
+
+        let mut buf = Vec::new();
+        source.create_bundle(&["main"], &mut buf).unwrap();
+        // Flip a byte well past the header/commit records, landing in a
+        // chunk payload, so its SHA-512 no longer matches its claimed key.
+        let tamper_at = buf.len() - 1;
+        buf[tamper_at] ^= 0xFF;
+
+        let result = dest.import_bundle(&buf[..]);
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&source_dir).ok();
+        std::fs::remove_dir_all(&dest_dir).ok();
+    }
+
+    #[test]
+    fn import_rejects_oversized_length_prefix_instead_of_allocating() {
+        let (dest, dest_dir) = test_repo("oversized");
+
+        let mut buf = Vec::new();
+        // A header length prefix far beyond MAX_HEADER_LEN must be
+        // rejected before any allocation is attempted.
+        buf.extend_from_slice(&(u64::MAX).to_le_bytes());
+
+        let result = dest.import_bundle(&buf[..]);
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&dest_dir).ok();
+    }
+}
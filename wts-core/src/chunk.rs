@@ -0,0 +1,237 @@
+//! Content-defined chunking and the chunk object store.
+//!
+//! Tensor bytes are split into content-defined chunks with a gear/buzhash
+//! rolling hash so that identical runs of bytes (e.g. unchanged layers
+//! across commits) produce identical chunks regardless of where they sit
+//! in the surrounding buffer. Chunks are addressed by their SHA-512 and
+//! written once under the `objects/chunks/<hash>` key of the repo's
+//! [`ObjectStore`](crate::store::ObjectStore), so a commit that only
+//! touches a few layers only pays for the bytes that actually changed,
+//! and a repo pointed at a remote backend (see
+//! [`Repository::with_store`](crate::Repository::with_store)) pushes and
+//! pulls chunk bytes through that backend too, not just commits and
+//! tensor manifests.
+
+use sha2::{Digest, Sha512};
+
+use crate::cipher::Cipher;
+use crate::store::ObjectStore;
+use crate::WTSError;
+
+const CHUNKS_PREFIX: &str = "objects/chunks";
+
+pub(crate) fn chunk_key(hash: &str) -> String {
+    format!("{CHUNKS_PREFIX}/{hash}")
+}
+
+/// Smallest chunk the content-defined chunker will emit.
+pub const MIN_CHUNK_SIZE: usize = 256 * 1024;
+/// Largest chunk the content-defined chunker will emit.
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Bitmask applied to the rolling hash; chosen so a boundary fires on
+/// average every `AVG_CHUNK_MASK + 1` bytes (~1 MiB).
+const AVG_CHUNK_MASK: u64 = (1 << 20) - 1;
+
+/// Deterministic 256-entry gear table used by the rolling hash. Built with
+/// a simple xorshift so it doesn't need an external RNG dependency; the
+/// exact values don't matter, only that they're fixed and well mixed.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        table[i] = seed;
+        i += 1;
+    }
+    table
+};
+
+/// Split `data` into content-defined chunks and return the byte ranges.
+///
+/// Uses a gear-hash rolling hash over a 64-byte window: a boundary is
+/// declared whenever `h & AVG_CHUNK_MASK == 0`, clamped so no chunk is
+/// smaller than [`MIN_CHUNK_SIZE`] or larger than [`MAX_CHUNK_SIZE`].
+pub fn chunk_boundaries(data: &[u8]) -> Vec<std::ops::Range<usize>> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranges = Vec::new();
+    let mut start = 0usize;
+    let mut h: u64 = 0;
+
+    for i in 0..data.len() {
+        h = (h << 1).wrapping_add(GEAR[data[i] as usize]);
+
+        let len = i + 1 - start;
+        if len < MIN_CHUNK_SIZE {
+            continue;
+        }
+        if len >= MAX_CHUNK_SIZE || h & AVG_CHUNK_MASK == 0 {
+            ranges.push(start..i + 1);
+            start = i + 1;
+            h = 0;
+        }
+    }
+
+    if start < data.len() {
+        ranges.push(start..data.len());
+    }
+
+    ranges
+}
+
+/// Content-addressed chunk store backed by the repo's [`ObjectStore`].
+pub struct ChunkStore<'a> {
+    store: &'a dyn ObjectStore,
+}
+
+impl<'a> ChunkStore<'a> {
+    pub fn new(store: &'a dyn ObjectStore) -> Self {
+        Self { store }
+    }
+
+    /// Hash and split `bytes`, writing any chunk not already present.
+    /// Returns the ordered list of hex-encoded chunk hashes. When
+    /// `cipher` is set, each chunk is sealed with the chunk's own hash
+    /// (its plaintext SHA-512) bound in as associated data.
+    pub fn put(&self, bytes: &[u8], cipher: Option<&Cipher>) -> Result<Vec<String>, WTSError> {
+        let mut hashes = Vec::new();
+        for range in chunk_boundaries(bytes) {
+            let chunk = &bytes[range];
+            let mut hasher = Sha512::new();
+            hasher.update(chunk);
+            let hash = hex::encode(hasher.finalize());
+
+            let key = chunk_key(&hash);
+            if !self.store.exists(&key)? {
+                let on_disk = match cipher {
+                    Some(cipher) => cipher.encrypt(chunk, hash.as_bytes())?,
+                    None => chunk.to_vec(),
+                };
+                self.store.put(&key, &on_disk)?;
+            }
+            hashes.push(hash);
+        }
+
+        Ok(hashes)
+    }
+
+    /// Reassemble a tensor's bytes from its ordered chunk hashes.
+    pub fn get(&self, hashes: &[String], cipher: Option<&Cipher>) -> Result<Vec<u8>, WTSError> {
+        let mut buffer = Vec::new();
+        for hash in hashes {
+            let on_disk = self
+                .store
+                .get(&chunk_key(hash))
+                .map_err(|_| WTSError::ObjectNotFound(format!("chunk {hash}")))?;
+            let chunk = match cipher {
+                Some(cipher) => cipher.decrypt(&on_disk, hash.as_bytes())?,
+                None => on_disk,
+            };
+            buffer.extend_from_slice(&chunk);
+        }
+        Ok(buffer)
+    }
+
+    /// Remove every chunk whose hash is not in `reachable`, returning how
+    /// many chunks were deleted and how many bytes were freed.
+    pub fn retain(&self, reachable: &std::collections::HashSet<String>) -> Result<(usize, u64), WTSError> {
+        let mut deleted = 0usize;
+        let mut freed = 0u64;
+
+        for key in self.store.list(CHUNKS_PREFIX)? {
+            let hash = key.rsplit('/').next().unwrap_or(&key);
+            if !reachable.contains(hash) {
+                let len = self.store.get(&key).map(|b| b.len() as u64).unwrap_or(0);
+                self.store.delete(&key)?;
+                deleted += 1;
+                freed += len;
+            }
+        }
+
+        Ok((deleted, freed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::LocalFsStore;
+    use std::path::PathBuf;
+
+    fn test_store(name: &str) -> (LocalFsStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wts-chunk-test-{name}-{}", std::process::id()));
+        (LocalFsStore::new(dir.clone()), dir)
+    }
+
+    #[test]
+    fn chunk_boundaries_are_deterministic() {
+        let data: Vec<u8> = (0..3 * MAX_CHUNK_SIZE).map(|i| (i % 251) as u8).collect();
+        let a = chunk_boundaries(&data);
+        let b = chunk_boundaries(&data);
+        assert_eq!(a, b);
+        assert!(a.iter().all(|r| r.len() >= MIN_CHUNK_SIZE || r.end == data.len()));
+        assert!(a.iter().all(|r| r.len() <= MAX_CHUNK_SIZE));
+    }
+
+    #[test]
+    fn identical_runs_produce_identical_chunks_for_dedup() {
+        // Each run and the filler are exactly MAX_CHUNK_SIZE long, so chunk
+        // boundaries fall at the run edges and `data` spans several chunks.
+        let run: Vec<u8> = (0..MAX_CHUNK_SIZE).map(|i| (i % 97) as u8).collect();
+        let mut data = run.clone();
+        data.extend(vec![0xAAu8; MAX_CHUNK_SIZE]);
+        data.extend(run.clone());
+
+        let (backing, dir) = test_store("dedup");
+        let store = ChunkStore::new(&backing);
+        let hashes = store.put(&data, None).unwrap();
+
+        let (run_backing, run_dir) = test_store("dedup-scratch");
+        let run_chunks = ChunkStore::new(&run_backing).put(&run, None).unwrap();
+        // The same plaintext run appears twice in `data`; each occurrence
+        // must hash (and therefore dedup) to the same chunk sequence.
+        assert_eq!(&hashes[..run_chunks.len()], &hashes[hashes.len() - run_chunks.len()..]);
+
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::remove_dir_all(&run_dir).ok();
+    }
+
+    #[test]
+    fn put_then_get_round_trips_bytes() {
+        let data: Vec<u8> = (0..2 * MAX_CHUNK_SIZE + 12345).map(|i| (i % 211) as u8).collect();
+        let (backing, dir) = test_store("roundtrip");
+        let store = ChunkStore::new(&backing);
+
+        let hashes = store.put(&data, None).unwrap();
+        let read_back = store.get(&hashes, None).unwrap();
+        assert_eq!(read_back, data);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn retain_deletes_unreachable_chunks_only() {
+        let (backing, dir) = test_store("retain");
+        let store = ChunkStore::new(&backing);
+
+        let data: Vec<u8> = (0..2 * MAX_CHUNK_SIZE + 12345).map(|i| (i % 131) as u8).collect();
+        let hashes = store.put(&data, None).unwrap();
+        let unique: std::collections::HashSet<String> = hashes.iter().cloned().collect();
+        assert!(unique.len() > 1, "test data must span multiple distinct chunks");
+
+        let kept_hash = hashes[0].clone();
+        let reachable: std::collections::HashSet<String> = [kept_hash.clone()].into_iter().collect();
+
+        let (deleted, freed) = store.retain(&reachable).unwrap();
+        assert_eq!(deleted, unique.len() - 1);
+        assert!(freed > 0);
+        assert!(store.get(std::slice::from_ref(&kept_hash), None).is_ok());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
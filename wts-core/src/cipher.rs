@@ -0,0 +1,128 @@
+//! At-rest encryption for objects and commits.
+//!
+//! A repository can optionally encrypt everything it writes under
+//! `.wts/objects` and `.wts/commits`. The key is derived from a user
+//! passphrase with Argon2id (memory-hard, so brute-forcing a stolen blob
+//! is expensive) using a random salt generated once and stored in
+//! `.wts/config`. Each blob is sealed with XChaCha20-Poly1305: a fresh
+//! 24-byte nonce is prepended to the ciphertext, and the blob's storage
+//! key (its content hash) is bound in as associated data, so a ciphertext
+//! swapped onto the wrong key fails to authenticate on load.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use crate::WTSError;
+
+/// Length in bytes of the random per-repo KDF salt.
+pub const SALT_LEN: usize = 16;
+/// Length in bytes of the XChaCha20 nonce prepended to each ciphertext.
+pub const NONCE_LEN: usize = 24;
+
+#[derive(Clone)]
+pub struct Cipher {
+    key: Key,
+}
+
+impl Cipher {
+    /// Generate a fresh random salt for a new encrypted repository.
+    pub fn generate_salt() -> [u8; SALT_LEN] {
+        let mut salt = [0u8; SALT_LEN];
+        use chacha20poly1305::aead::rand_core::RngCore;
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    /// Derive a 256-bit key from `passphrase` and `salt` with Argon2id.
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, WTSError> {
+        let mut key_bytes = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key_bytes)
+            .map_err(|e| WTSError::Other(format!("key derivation failed: {e}")))?;
+        Ok(Self {
+            key: *Key::from_slice(&key_bytes),
+        })
+    }
+
+    /// Encrypt `plaintext`, binding `aad` (the blob's storage key) into
+    /// the AEAD tag. Returns `nonce || ciphertext`.
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, WTSError> {
+        let cipher = XChaCha20Poly1305::new(&self.key);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|_| WTSError::Other("encryption failed".to_string()))?;
+
+        let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypt a `nonce || ciphertext` blob, verifying `aad`. Fails with
+    /// [`WTSError::DecryptionFailed`] when the passphrase is wrong or the
+    /// blob has been tampered with (AEAD tag mismatch).
+    pub fn decrypt(&self, blob: &[u8], aad: &[u8]) -> Result<Vec<u8>, WTSError> {
+        if blob.len() < NONCE_LEN {
+            return Err(WTSError::DecryptionFailed);
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let nonce = XNonce::from_slice(nonce);
+        let cipher = XChaCha20Poly1305::new(&self.key);
+
+        cipher
+            .decrypt(nonce, Payload { msg: ciphertext, aad })
+            .map_err(|_| WTSError::DecryptionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_then_decrypt_round_trips() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::derive("correct horse battery staple", &salt).unwrap();
+
+        let plaintext = b"top secret tensor bytes";
+        let sealed = cipher.encrypt(plaintext, b"object-key").unwrap();
+        let opened = cipher.decrypt(&sealed, b"object-key").unwrap();
+
+        assert_eq!(opened, plaintext);
+    }
+
+    #[test]
+    fn decrypt_fails_with_wrong_passphrase() {
+        let salt = Cipher::generate_salt();
+        let right = Cipher::derive("correct horse battery staple", &salt).unwrap();
+        let wrong = Cipher::derive("incorrect horse", &salt).unwrap();
+
+        let sealed = right.encrypt(b"payload", b"aad").unwrap();
+        let result = wrong.decrypt(&sealed, b"aad");
+
+        assert!(matches!(result, Err(WTSError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn decrypt_fails_with_mismatched_aad() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::derive("passphrase", &salt).unwrap();
+
+        let sealed = cipher.encrypt(b"payload", b"original-key").unwrap();
+        let result = cipher.decrypt(&sealed, b"different-key");
+
+        assert!(matches!(result, Err(WTSError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn decrypt_rejects_truncated_blob() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::derive("passphrase", &salt).unwrap();
+
+        let result = cipher.decrypt(&[0u8; NONCE_LEN - 1], b"aad");
+        assert!(matches!(result, Err(WTSError::DecryptionFailed)));
+    }
+}
@@ -0,0 +1,243 @@
+//! Tensor-aware diffing between two commits.
+
+use candle::{DType, Device, Tensor};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::cipher::Cipher;
+use crate::{Repository, WTSError};
+
+/// Numerical comparison between two same-shaped tensors.
+#[derive(Debug, Serialize)]
+pub struct TensorDiffStats {
+    pub l2_norm: f64,
+    pub max_abs_diff: f64,
+    pub mean_abs_diff: f64,
+    pub cosine_similarity: f64,
+}
+
+/// How a single tensor name changed between two commits.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum TensorDiff {
+    Added {
+        shape: Vec<usize>,
+        dtype: String,
+    },
+    Removed {
+        shape: Vec<usize>,
+        dtype: String,
+    },
+    Modified {
+        from_shape: Vec<usize>,
+        to_shape: Vec<usize>,
+        from_dtype: String,
+        to_dtype: String,
+        /// `None` when the shapes differ and no elementwise comparison
+        /// is possible.
+        stats: Option<TensorDiffStats>,
+    },
+}
+
+/// Structured report produced by [`Repository::diff`].
+#[derive(Debug, Serialize)]
+pub struct CommitDiff {
+    pub from: String,
+    pub to: String,
+    pub tensors: HashMap<String, TensorDiff>,
+}
+
+fn to_wts_err(e: impl ToString) -> WTSError {
+    WTSError::SafeTensorError(e.to_string())
+}
+
+fn tensor_bytes_equal(a: &Tensor, b: &Tensor) -> Result<bool, WTSError> {
+    let mut a_bytes = Vec::new();
+    let mut b_bytes = Vec::new();
+    a.write_bytes(&mut a_bytes).map_err(to_wts_err)?;
+    b.write_bytes(&mut b_bytes).map_err(to_wts_err)?;
+    Ok(a_bytes == b_bytes)
+}
+
+fn compute_stats(a: &Tensor, b: &Tensor) -> Result<TensorDiffStats, WTSError> {
+    let a = a.flatten_all().and_then(|t| t.to_dtype(DType::F32)).map_err(to_wts_err)?;
+    let b = b.flatten_all().and_then(|t| t.to_dtype(DType::F32)).map_err(to_wts_err)?;
+    let a_vals = a.to_vec1::<f32>().map_err(to_wts_err)?;
+    let b_vals = b.to_vec1::<f32>().map_err(to_wts_err)?;
+
+    let mut sum_sq = 0f64;
+    let mut max_abs = 0f64;
+    let mut sum_abs = 0f64;
+    let mut dot = 0f64;
+    let mut norm_a = 0f64;
+    let mut norm_b = 0f64;
+
+    for (x, y) in a_vals.iter().zip(b_vals.iter()) {
+        let (x, y) = (*x as f64, *y as f64);
+        let d = x - y;
+        sum_sq += d * d;
+        max_abs = max_abs.max(d.abs());
+        sum_abs += d.abs();
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    let count = a_vals.len().max(1) as f64;
+    let cosine_similarity = if norm_a > 0.0 && norm_b > 0.0 {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    } else {
+        0.0
+    };
+
+    Ok(TensorDiffStats {
+        l2_norm: sum_sq.sqrt(),
+        max_abs_diff: max_abs,
+        mean_abs_diff: sum_abs / count,
+        cosine_similarity,
+    })
+}
+
+impl Repository {
+    fn load_commit_tensors(
+        &self,
+        hash: &str,
+        device: &Device,
+        cipher: Option<&Cipher>,
+    ) -> Result<HashMap<String, Tensor>, WTSError> {
+        let commit = self.get_commit_with_cipher(hash, cipher)?;
+        let object_hash = hex::encode(&commit.hash);
+        self.get_obj_with_cipher(&object_hash, device, cipher)
+    }
+
+    /// Compare the tensors committed at `from` against `to`, reporting
+    /// which tensors were added, removed, or modified. For tensors whose
+    /// shape is unchanged, also reports the L2 norm, max/mean absolute
+    /// difference, and cosine similarity between the flattened values.
+    pub fn diff(
+        &self,
+        from: &str,
+        to: &str,
+        device: &Device,
+        passphrase: Option<&str>,
+    ) -> Result<CommitDiff, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        let from_tensors = self.load_commit_tensors(from, device, cipher.as_ref())?;
+        let to_tensors = self.load_commit_tensors(to, device, cipher.as_ref())?;
+
+        let mut names: BTreeSet<&String> = from_tensors.keys().collect();
+        names.extend(to_tensors.keys());
+
+        let mut tensors = HashMap::new();
+        for name in names {
+            match (from_tensors.get(name), to_tensors.get(name)) {
+                (None, Some(t)) => {
+                    tensors.insert(
+                        name.clone(),
+                        TensorDiff::Added {
+                            shape: t.dims().to_vec(),
+                            dtype: t.dtype().as_str().to_string(),
+                        },
+                    );
+                }
+                (Some(t), None) => {
+                    tensors.insert(
+                        name.clone(),
+                        TensorDiff::Removed {
+                            shape: t.dims().to_vec(),
+                            dtype: t.dtype().as_str().to_string(),
+                        },
+                    );
+                }
+                (Some(a), Some(b)) => {
+                    if a.dims() == b.dims() && a.dtype() == b.dtype() && tensor_bytes_equal(a, b)? {
+                        continue;
+                    }
+
+                    let stats = if a.dims() == b.dims() {
+                        Some(compute_stats(a, b)?)
+                    } else {
+                        None
+                    };
+
+                    tensors.insert(
+                        name.clone(),
+                        TensorDiff::Modified {
+                            from_shape: a.dims().to_vec(),
+                            to_shape: b.dims().to_vec(),
+                            from_dtype: a.dtype().as_str().to_string(),
+                            to_dtype: b.dtype().as_str().to_string(),
+                            stats,
+                        },
+                    );
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+
+        Ok(CommitDiff {
+            from: from.to_string(),
+            to: to.to_string(),
+            tensors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_tensors_are_bytes_equal() {
+        let device = Device::Cpu;
+        let a = Tensor::new(&[1.0f32, 2.0, 3.0], &device).unwrap();
+        let b = Tensor::new(&[1.0f32, 2.0, 3.0], &device).unwrap();
+        assert!(tensor_bytes_equal(&a, &b).unwrap());
+    }
+
+    #[test]
+    fn differing_tensors_are_not_bytes_equal() {
+        let device = Device::Cpu;
+        let a = Tensor::new(&[1.0f32, 2.0, 3.0], &device).unwrap();
+        let b = Tensor::new(&[1.0f32, 2.0, 4.0], &device).unwrap();
+        assert!(!tensor_bytes_equal(&a, &b).unwrap());
+    }
+
+    #[test]
+    fn compute_stats_is_zero_for_identical_tensors() {
+        let device = Device::Cpu;
+        let a = Tensor::new(&[1.0f32, -2.0, 3.0], &device).unwrap();
+        let b = Tensor::new(&[1.0f32, -2.0, 3.0], &device).unwrap();
+
+        let stats = compute_stats(&a, &b).unwrap();
+        assert_eq!(stats.l2_norm, 0.0);
+        assert_eq!(stats.max_abs_diff, 0.0);
+        assert_eq!(stats.mean_abs_diff, 0.0);
+        assert!((stats.cosine_similarity - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn compute_stats_reports_known_differences() {
+        let device = Device::Cpu;
+        let a = Tensor::new(&[1.0f32, 0.0], &device).unwrap();
+        let b = Tensor::new(&[0.0f32, 1.0], &device).unwrap();
+
+        let stats = compute_stats(&a, &b).unwrap();
+        assert!((stats.l2_norm - 2f64.sqrt()).abs() < 1e-6);
+        assert_eq!(stats.max_abs_diff, 1.0);
+        assert_eq!(stats.mean_abs_diff, 1.0);
+        // Orthogonal vectors have zero dot product (and unit norms), so
+        // cosine similarity is 0 without taking the zero-norm fallback.
+        assert_eq!(stats.cosine_similarity, 0.0);
+    }
+
+    #[test]
+    fn compute_stats_zero_vector_falls_back_to_zero_cosine_similarity() {
+        let device = Device::Cpu;
+        let a = Tensor::new(&[0.0f32, 0.0], &device).unwrap();
+        let b = Tensor::new(&[0.0f32, 0.0], &device).unwrap();
+
+        let stats = compute_stats(&a, &b).unwrap();
+        assert_eq!(stats.cosine_similarity, 0.0);
+    }
+}
@@ -0,0 +1,261 @@
+//! Append-only index over the commit log.
+//!
+//! Commits used to be stored as one pretty-printed JSON file per hash,
+//! so walking history meant opening and fully parsing a file (message,
+//! metadata, signature and all) at every hop just to learn the parent
+//! hash. Commits now live back-to-back in a single binary log blob,
+//! `commits/log.bin`, and `commits/index.bin` records one small fixed
+//! entry per commit — its parent hash, timestamp, and byte offset/length
+//! in the log. Both blobs are read and written through the repo's
+//! [`ObjectStore`], the same as any other object, so a `Repository`
+//! pointed at a remote backend (see [`Repository::with_store`]) can push
+//! and pull its commit history through that backend too, not just
+//! tensor objects. [`CommitIndex::load`] parses the whole index blob
+//! into a hash map; after that, following a commit's ancestry is pure
+//! in-memory lookups, with the log blob read only when the full commit
+//! record is actually needed.
+//!
+//! Appends and single-record reads go through
+//! [`ObjectStore::append`]/[`ObjectStore::get_range`] rather than a full
+//! read-modify-write over [`ObjectStore::get`]/[`ObjectStore::put`], so
+//! [`LocalFsStore`](crate::store::LocalFsStore) — the common case — does
+//! a true O(1) disk append per commit and a true seek-based read per
+//! commit lookup, the same cost the original file-backed log had before
+//! it moved behind this trait. Backends that can't seek or append fall
+//! back to the trait's default whole-blob behavior, which is correct but
+//! not free. `CommitIndex::load` rescans the whole index blob into a hash
+//! map on every call, since building the lookup table needs every entry.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::store::ObjectStore;
+use crate::time::UnixTimestamp;
+use crate::{Repository, WTSError};
+
+/// Key under which the raw, back-to-back commit records live.
+const COMMIT_LOG_KEY: &str = "commits/log.bin";
+/// Key under which the bincode-encoded `(hex_hash, IndexEntry)` records
+/// live, each prefixed with a `u32` LE length.
+const COMMIT_INDEX_KEY: &str = "commits/index.bin";
+
+/// One entry in the commit index: everything needed to find a commit's
+/// parent and locate its record in the commit log, without touching the
+/// record itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub parent_hash: Option<Vec<u8>>,
+    pub timestamp: UnixTimestamp,
+    pub offset: u64,
+    pub len: u32,
+}
+
+/// Read a blob that may not exist yet (a fresh repo has no commit log or
+/// index), treating "not found" as empty rather than an error.
+fn get_or_empty(store: &dyn ObjectStore, key: &str) -> Result<Vec<u8>, WTSError> {
+    match store.get(key) {
+        Ok(bytes) => Ok(bytes),
+        Err(WTSError::ObjectNotFound(_)) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Slice `bytes[cursor..cursor + len]`, turning an out-of-range request
+/// (a truncated or corrupted blob) into a decode error instead of a
+/// panic.
+fn checked_slice(bytes: &[u8], cursor: usize, len: usize) -> Result<&[u8], WTSError> {
+    bytes
+        .get(cursor..cursor + len)
+        .ok_or_else(|| WTSError::SafeTensorError("commit index truncated or corrupt".to_string()))
+}
+
+/// In-memory view of the commit index, keyed by hex commit hash.
+#[derive(Debug, Default)]
+pub struct CommitIndex {
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl CommitIndex {
+    /// Parse every entry out of `commits/index.bin`. An empty
+    /// (not-yet-written) index is not an error: a fresh repository has
+    /// none yet.
+    pub fn load(store: &dyn ObjectStore) -> Result<Self, WTSError> {
+        let bytes = get_or_empty(store, COMMIT_INDEX_KEY)?;
+
+        let mut entries = HashMap::new();
+        let mut cursor = 0usize;
+        while cursor < bytes.len() {
+            let len_bytes = checked_slice(&bytes, cursor, 4)?;
+            let record_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+            cursor += 4;
+
+            let record = checked_slice(&bytes, cursor, record_len)?;
+            let (hex_hash, entry): (String, IndexEntry) = bincode::deserialize(record)
+                .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+            cursor += record_len;
+
+            entries.insert(hex_hash, entry);
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, hex_hash: &str) -> Option<&IndexEntry> {
+        self.entries.get(hex_hash)
+    }
+}
+
+impl Repository {
+    /// Append a commit's on-disk bytes to the commit log and record its
+    /// parent hash, timestamp, and byte range in the commit index.
+    pub(crate) fn append_commit_bytes(
+        &self,
+        hex_hash: &str,
+        parent_hash: Option<Vec<u8>>,
+        timestamp: UnixTimestamp,
+        on_disk: &[u8],
+    ) -> Result<(), WTSError> {
+        let offset = self.store.append(COMMIT_LOG_KEY, on_disk)?;
+
+        let entry = IndexEntry {
+            parent_hash,
+            timestamp,
+            offset,
+            len: on_disk.len() as u32,
+        };
+        let record = bincode::serialize(&(hex_hash.to_string(), entry))
+            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+
+        let mut record_with_len = Vec::with_capacity(4 + record.len());
+        record_with_len.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        record_with_len.extend_from_slice(&record);
+        self.store.append(COMMIT_INDEX_KEY, &record_with_len)?;
+        Ok(())
+    }
+
+    /// Raw (possibly still-encrypted) bytes of a commit record, read
+    /// straight from the commit log via the index, with no decryption or
+    /// deserialization. Used to copy commits into a bundle verbatim.
+    pub(crate) fn raw_commit_bytes(&self, hex_hash: &str) -> Result<Vec<u8>, WTSError> {
+        let index = CommitIndex::load(self.store.as_ref())?;
+        let entry = index
+            .get(hex_hash)
+            .ok_or_else(|| WTSError::ObjectNotFound(format!("commit {hex_hash}")))?;
+
+        self.store.get_range(COMMIT_LOG_KEY, entry.offset, entry.len)
+    }
+
+    /// Whether a commit is already present in the local index.
+    pub(crate) fn has_commit_record(&self, hex_hash: &str) -> Result<bool, WTSError> {
+        Ok(CommitIndex::load(self.store.as_ref())?.get(hex_hash).is_some())
+    }
+
+    /// Index and store a commit record that arrived verbatim from a
+    /// bundle. The record must be unencrypted: recovering its parent
+    /// hash and timestamp for the index means deserializing it, and a
+    /// bundle of encrypted commits can't be decrypted without the source
+    /// repo's passphrase.
+    pub(crate) fn import_commit_record(&self, hex_hash: &str, on_disk: Vec<u8>) -> Result<(), WTSError> {
+        let commit = crate::decode_commit(&on_disk).map_err(|_| {
+            WTSError::Other(format!(
+                "commit {hex_hash} could not be indexed (bundles of encrypted commits aren't supported)"
+            ))
+        })?;
+
+        if hex::encode(&commit.hash) != hex_hash {
+            return Err(WTSError::Other(format!(
+                "commit {hex_hash} failed integrity check: record hash does not match its claimed bundle key"
+            )));
+        }
+
+        self.append_commit_bytes(hex_hash, commit.parent_hash, commit.timestamp, &on_disk)
+    }
+
+    /// Walk a commit's ancestry using only the index — no reads of the
+    /// commit log, no decryption, no parsing of message/metadata/
+    /// signature — returning `(hex_hash, IndexEntry)` pairs from `start`
+    /// back to the root. This is what makes `log` fast over long
+    /// histories: callers who want full commit data can still fetch it
+    /// per hash afterward.
+    pub fn log(&self, start_hash: &str) -> Result<Vec<(String, IndexEntry)>, WTSError> {
+        let index = CommitIndex::load(self.store.as_ref())?;
+
+        let mut out = Vec::new();
+        let mut current = Some(start_hash.to_string());
+        while let Some(hex_hash) = current {
+            let entry = index
+                .get(&hex_hash)
+                .ok_or_else(|| WTSError::ObjectNotFound(format!("commit {hex_hash}")))?
+                .clone();
+            current = entry.parent_hash.as_ref().map(hex::encode);
+            out.push((hex_hash, entry));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::LocalFsStore;
+    use chrono::Utc;
+
+    fn test_repo(name: &str) -> (Repository, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wts-index-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::with_store(&dir, Box::new(LocalFsStore::new(dir.clone())));
+        (repo, dir)
+    }
+
+    #[test]
+    fn append_then_load_round_trips() {
+        let (repo, dir) = test_repo("roundtrip");
+
+        repo.append_commit_bytes("aa", None, UnixTimestamp(Utc::now()), b"commit one bytes")
+            .unwrap();
+        repo.append_commit_bytes("bb", Some(vec![0xaa]), UnixTimestamp(Utc::now()), b"commit two bytes")
+            .unwrap();
+
+        let index = CommitIndex::load(repo.store.as_ref()).unwrap();
+        assert_eq!(index.get("aa").unwrap().parent_hash, None);
+        assert_eq!(index.get("bb").unwrap().parent_hash, Some(vec![0xaa]));
+
+        assert_eq!(repo.raw_commit_bytes("aa").unwrap(), b"commit one bytes");
+        assert_eq!(repo.raw_commit_bytes("bb").unwrap(), b"commit two bytes");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn log_walks_parent_chain_in_order() {
+        let (repo, dir) = test_repo("log");
+
+        repo.append_commit_bytes("aa", None, UnixTimestamp(Utc::now()), b"root")
+            .unwrap();
+        repo.append_commit_bytes("bb", Some(vec![0xaa]), UnixTimestamp(Utc::now()), b"child")
+            .unwrap();
+
+        let entries = repo.log("bb").unwrap();
+        let hashes: Vec<&str> = entries.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(hashes, vec!["bb", "aa"]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_rejects_truncated_index_instead_of_panicking() {
+        let (repo, dir) = test_repo("truncated");
+
+        repo.append_commit_bytes("aa", None, UnixTimestamp(Utc::now()), b"root")
+            .unwrap();
+
+        let mut bytes = repo.store.get(COMMIT_INDEX_KEY).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        repo.store.put(COMMIT_INDEX_KEY, &bytes).unwrap();
+
+        let result = CommitIndex::load(repo.store.as_ref());
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,12 +1,11 @@
-use candle::safetensors::{load, save};
-use candle::{Device, Tensor};
+use candle::{DType, Device, Tensor};
 use chrono::prelude::*;
 use safetensors::SafeTensors;
 use sha2::{Digest, Sha512};
 use time::UnixTimestamp;
 
-use std::collections::HashMap;
-use std::fs::{self};
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::{
     io,
     path::{Path, PathBuf},
@@ -15,11 +14,23 @@ use thiserror::Error;
 
 use serde::{Deserialize, Serialize};
 
+pub mod bundle;
+pub mod chunk;
+pub mod cipher;
+pub mod diff;
+pub mod index;
+pub mod sign;
+pub mod store;
+
+use chunk::ChunkStore;
+use cipher::Cipher;
+use store::{LocalFsStore, ObjectStore};
+
 pub mod time {
     use chrono::{DateTime, NaiveDateTime, Utc};
     use serde::{Deserialize, Serialize};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct UnixTimestamp(pub DateTime<Utc>);
     const FORMAT: &'static str = "%s.%6f";
 
@@ -61,15 +72,109 @@ pub enum WTSError {
     SafeTensorError(String),
     #[error("Other error: {0}")]
     Other(String),
+    #[error("Unsupported tensor dtype: {0}")]
+    UnsupportedDType(String),
+    #[error("Decryption failed: wrong passphrase or corrupted object")]
+    DecryptionFailed,
+    #[error("Repository is encrypted but no passphrase was provided")]
+    PassphraseRequired,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Commit {
+    pub(crate) hash: Vec<u8>,
+    pub(crate) parent_hash: Option<Vec<u8>>,
+    pub(crate) timestamp: time::UnixTimestamp,
+    pub(crate) message: String,
+    pub(crate) metadata: serde_json::Value,
+    #[serde(default)]
+    pub(crate) author: Option<sign::Identity>,
+    #[serde(default)]
+    pub(crate) signature: Option<sign::CommitSignature>,
+}
+
+/// On-disk twin of [`Commit`] used only at the bincode encode/decode
+/// boundary. `serde_json::Value`'s `Deserialize` impl always calls
+/// `deserialize_any`, which bincode's deserializer doesn't implement, so
+/// `metadata` is carried as an already-serialized JSON string instead of
+/// a raw `Value` in the envelope bincode actually sees.
+#[derive(Deserialize, Serialize)]
+struct CommitWire {
     hash: Vec<u8>,
     parent_hash: Option<Vec<u8>>,
     timestamp: time::UnixTimestamp,
     message: String,
-    metadata: serde_json::Value,
+    metadata_json: String,
+    author: Option<sign::Identity>,
+    signature: Option<sign::CommitSignature>,
+}
+
+/// Encode a [`Commit`] into the bytes stored in the commit log.
+pub(crate) fn encode_commit(commit: &Commit) -> Result<Vec<u8>, WTSError> {
+    let metadata_json = serde_json::to_string(&commit.metadata)
+        .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+    let wire = CommitWire {
+        hash: commit.hash.clone(),
+        parent_hash: commit.parent_hash.clone(),
+        timestamp: commit.timestamp.clone(),
+        message: commit.message.clone(),
+        metadata_json,
+        author: commit.author.clone(),
+        signature: commit.signature.clone(),
+    };
+    bincode::serialize(&wire).map_err(|e| WTSError::SafeTensorError(e.to_string()))
+}
+
+/// Decode a [`Commit`] from bytes produced by [`encode_commit`].
+pub(crate) fn decode_commit(bytes: &[u8]) -> Result<Commit, WTSError> {
+    let wire: CommitWire =
+        bincode::deserialize(bytes).map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+    let metadata = serde_json::from_str(&wire.metadata_json)
+        .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+    Ok(Commit {
+        hash: wire.hash,
+        parent_hash: wire.parent_hash,
+        timestamp: wire.timestamp,
+        message: wire.message,
+        metadata,
+        author: wire.author,
+        signature: wire.signature,
+    })
+}
+
+/// On-disk record of a commit's tensors: for each tensor, its shape,
+/// dtype, and the ordered list of content-defined chunk hashes that make
+/// up its raw bytes. Replaces storing a monolithic SafeTensors blob per
+/// commit, so unchanged tensors across commits share chunks on disk.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TensorManifestEntry {
+    pub shape: Vec<usize>,
+    pub dtype: String,
+    pub chunks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ObjectManifest {
+    pub tensors: HashMap<String, TensorManifestEntry>,
+}
+
+/// Result of a `gc` pass over the chunk store.
+#[derive(Debug)]
+pub struct GcReport {
+    pub chunks_deleted: usize,
+    pub bytes_freed: u64,
+}
+
+/// Repo-level settings persisted at `.wts/config`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RepoConfig {
+    pub encrypted: bool,
+    /// Hex-encoded Argon2id salt, set only when `encrypted` is true.
+    pub kdf_salt: Option<String>,
+    /// Hex-encoded Ed25519 signing key seed, used by `create_signed_commit`;
+    /// when unset, the `WTS_SIGNING_KEY` environment variable is used.
+    #[serde(default)]
+    pub signing_key: Option<String>,
 }
 
 pub struct Refernce {
@@ -85,6 +190,7 @@ pub struct OwnedSafeTensor {
 #[derive(Debug)]
 pub struct Repository {
     pub root: PathBuf,
+    pub store: Box<dyn ObjectStore>,
 }
 
 impl Repository {
@@ -92,31 +198,94 @@ impl Repository {
         let root = path.as_ref().to_path_buf();
         if !root.exists() {
             fs::create_dir_all(&root.join(".wts"))?;
-        } 
-        Ok(Self { root })
+        }
+        let store = Box::new(LocalFsStore::new(root.join(".wts")));
+        Ok(Self { root, store })
     }
 
     pub fn open() -> Result<Self, WTSError>{
         let root = std::env::current_dir().map_err(|e| WTSError::Io(e))?;
         if !root.exists() {
             return Err(WTSError::EmptyRepository);
-        } 
-        Ok(Self { root })
+        }
+        let store = Box::new(LocalFsStore::new(root.join(".wts")));
+        Ok(Self { root, store })
+    }
+
+    /// Open a repository rooted at `path` against a custom object store,
+    /// e.g. an [`store::HttpObjectStore`] pointed at a shared bucket URL
+    /// instead of the local `.wts` directory.
+    pub fn with_store<P: AsRef<Path>>(path: P, store: Box<dyn ObjectStore>) -> Self {
+        Self {
+            root: path.as_ref().to_path_buf(),
+            store,
+        }
     }
 
-    pub fn init(&self) -> io::Result<()> {
+    pub fn init(&self) -> Result<(), WTSError> {
+        self.init_with_encryption(false)
+    }
+
+    /// Initialize the repository, optionally enabling at-rest encryption.
+    /// When `encrypted` is true, a fresh random KDF salt is generated and
+    /// recorded in `.wts/config`; every object and commit written from
+    /// then on must be opened with the matching passphrase.
+    pub fn init_with_encryption(&self, encrypted: bool) -> Result<(), WTSError> {
         let wts_dir = self.root.join(".wts");
 
         fs::create_dir_all(wts_dir.join("objects"))?;
         fs::create_dir_all(wts_dir.join("refs/heads"))?;
         fs::create_dir_all(wts_dir.join("refs/tags"))?;
-        fs::create_dir_all(wts_dir.join("commits"))?;
 
         fs::write(wts_dir.join("HEAD"), r#"ref: ref/heads/main"#)?;
 
+        let config = RepoConfig {
+            encrypted,
+            kdf_salt: encrypted.then(|| hex::encode(Cipher::generate_salt())),
+            signing_key: None,
+        };
+        self.write_config(&config)?;
+
+        Ok(())
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.root.join(".wts").join("config")
+    }
+
+    pub fn read_config(&self) -> Result<RepoConfig, WTSError> {
+        match fs::read_to_string(self.config_path()) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| WTSError::SafeTensorError(e.to_string())),
+            Err(_) => Ok(RepoConfig::default()),
+        }
+    }
+
+    fn write_config(&self, config: &RepoConfig) -> Result<(), WTSError> {
+        let json = serde_json::to_string_pretty(config)
+            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+        fs::write(self.config_path(), json)?;
         Ok(())
     }
 
+    /// Resolve the repo's `Cipher` from its config, deriving the key from
+    /// `passphrase` if encryption is enabled. Returns `None` for
+    /// unencrypted repositories.
+    fn cipher(&self, passphrase: Option<&str>) -> Result<Option<Cipher>, WTSError> {
+        let config = self.read_config()?;
+        if !config.encrypted {
+            return Ok(None);
+        }
+
+        let passphrase = passphrase.ok_or(WTSError::PassphraseRequired)?;
+        let salt = config
+            .kdf_salt
+            .ok_or_else(|| WTSError::Other("encrypted repo missing kdf_salt".to_string()))?;
+        let salt = hex::decode(salt).map_err(|e| WTSError::Other(e.to_string()))?;
+
+        Ok(Some(Cipher::derive(passphrase, &salt)?))
+    }
+
     pub fn is_initialized(&self) -> bool {
         self.root.join(".wts").exists()
     }
@@ -127,34 +296,37 @@ impl Repository {
         message: &str,
         metadata: serde_json::Value,
         parent: Option<Vec<u8>>,
+        passphrase: Option<&str>,
     ) -> Result<String, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+
         // Generate hash from tensors
         let hash = self.hash_tensors(tensors)?;
 
         let hex_hash = hex::encode(&hash);
+        let timestamp = UnixTimestamp(Utc::now());
 
         // Create commit object
         let commit = Commit {
             hash: hash.to_vec().clone(),
-            parent_hash: parent,
-            timestamp: UnixTimestamp(Utc::now()),
+            parent_hash: parent.clone(),
+            timestamp: timestamp.clone(),
             message: message.to_string(),
             metadata,
+            author: None,
+            signature: None,
         };
 
         // Save commit
-        let commit_path = self
-            .root
-            .join(".wts")
-            .join("commits")
-            .join(format!("{}.json", hex_hash));
-
-        let commit_json = serde_json::to_string_pretty(&commit)
-            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
-        fs::write(commit_path, commit_json)?;
+        let commit_bytes = encode_commit(&commit)?;
+        let on_disk = match &cipher {
+            Some(cipher) => cipher.encrypt(&commit_bytes, hex_hash.as_bytes())?,
+            None => commit_bytes,
+        };
+        self.append_commit_bytes(&hex_hash, parent, timestamp, &on_disk)?;
 
         // Save tensors
-        self.store_tensors(tensors, &hex_hash)?;
+        self.store_tensors(tensors, &hex_hash, cipher.as_ref())?;
 
         Ok(hex_hash)
     }
@@ -188,54 +360,226 @@ impl Repository {
         Ok(buffer)
     }
 
-    fn store_tensors(&self, tensors: &HashMap<String, Tensor>, hash: &str) -> Result<(), WTSError> {
-        let object_path = self.root.join(".wts").join("objects").join(hash);
-        let _ = save(tensors, object_path)
-            .map(|_| WTSError::SafeTensorError(format!("Could not save tensor")));
+    fn store_tensors(
+        &self,
+        tensors: &HashMap<String, Tensor>,
+        hash: &str,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), WTSError> {
+        let chunk_store = ChunkStore::new(self.store.as_ref());
+
+        let mut manifest = ObjectManifest {
+            tensors: HashMap::new(),
+        };
+
+        for (name, tensor) in tensors {
+            let mut buffer = Vec::new();
+            tensor
+                .write_bytes(&mut buffer)
+                .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+
+            let chunks = chunk_store.put(&buffer, cipher)?;
+
+            manifest.tensors.insert(
+                name.clone(),
+                TensorManifestEntry {
+                    shape: tensor.dims().to_vec(),
+                    dtype: tensor.dtype().as_str().to_string(),
+                    chunks,
+                },
+            );
+        }
+
+        let manifest_json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+        let on_disk = match cipher {
+            Some(cipher) => cipher.encrypt(manifest_json.as_bytes(), hash.as_bytes())?,
+            None => manifest_json.into_bytes(),
+        };
+        self.store.put(&object_key(hash), &on_disk)?;
+
         Ok(())
     }
 
     pub fn create_branch(&self, name: &str, commit_hash: &str) -> Result<(), WTSError> {
-        let ref_path = self.root.join(".wts").join("refs").join("heads").join(name);
-
-        fs::write(ref_path, commit_hash)?;
+        self.store.put(&format!("refs/heads/{name}"), commit_hash.as_bytes())?;
         Ok(())
     }
 
     pub fn create_tag(&self, name: &str, commit_hash: &str) -> Result<(), WTSError> {
-        let tag_path = self.root.join(".wts").join("refs").join("tags").join(name);
-
-        fs::write(tag_path, commit_hash)?;
+        self.store.put(&format!("refs/tags/{name}"), commit_hash.as_bytes())?;
         Ok(())
     }
 
     pub fn get_commit(&self, hash: &str) -> Result<Commit, WTSError> {
-        let commit_path = self
-            .root
-            .join(".wts")
-            .join("commits")
-            .join(format!("{}.json", hash));
-
-        let commit_json = fs::read_to_string(commit_path)?;
-        serde_json::from_str(&commit_json).map_err(|e| WTSError::SafeTensorError(e.to_string()))
+        self.get_commit_with_passphrase(hash, None)
+    }
+
+    pub fn get_commit_with_passphrase(
+        &self,
+        hash: &str,
+        passphrase: Option<&str>,
+    ) -> Result<Commit, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        self.get_commit_with_cipher(hash, cipher.as_ref())
+    }
+
+    /// Like [`Repository::get_commit_with_passphrase`], but takes an
+    /// already-derived `Cipher` instead of a passphrase. Argon2id is
+    /// deliberately slow, so callers that need many commits in one
+    /// operation (history traversal, `gc`, `diff`) should derive the
+    /// cipher once and reuse it here rather than paying a fresh KDF pass
+    /// per commit.
+    pub(crate) fn get_commit_with_cipher(
+        &self,
+        hash: &str,
+        cipher: Option<&Cipher>,
+    ) -> Result<Commit, WTSError> {
+        let on_disk = self.raw_commit_bytes(hash)?;
+
+        let commit_bytes = match cipher {
+            Some(cipher) => cipher.decrypt(&on_disk, hash.as_bytes())?,
+            None => on_disk,
+        };
+        decode_commit(&commit_bytes)
     }
 
     pub fn get_reference(&self, ref_path: &str) -> Result<String, WTSError> {
-        let full_path = self.root.join(".wts").join(ref_path);
-        match fs::read_to_string(full_path) {
-            Ok(hash) => Ok(hash.trim().to_string()),
+        match self.store.get(ref_path) {
+            Ok(bytes) => {
+                let hash = String::from_utf8(bytes)
+                    .map_err(|_| WTSError::InvalidReference(ref_path.to_string()))?;
+                Ok(hash.trim().to_string())
+            }
             Err(_) => Err(WTSError::InvalidReference(ref_path.to_string())),
         }
     }
 
-    pub fn get_obj(&self, path : &str, device : &Device) -> Result<HashMap<String, Tensor>, WTSError> {
-        load(path, device).map_err(|e| WTSError::Other(e.to_string()))
+    pub fn get_obj(&self, hash: &str, device: &Device) -> Result<HashMap<String, Tensor>, WTSError> {
+        self.get_obj_with_passphrase(hash, device, None)
+    }
+
+    pub fn get_obj_with_passphrase(
+        &self,
+        hash: &str,
+        device: &Device,
+        passphrase: Option<&str>,
+    ) -> Result<HashMap<String, Tensor>, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        self.get_obj_with_cipher(hash, device, cipher.as_ref())
+    }
+
+    /// Like [`Repository::get_obj_with_passphrase`], but takes an
+    /// already-derived `Cipher` instead of a passphrase; see
+    /// [`Repository::get_commit_with_cipher`] for why that matters.
+    pub(crate) fn get_obj_with_cipher(
+        &self,
+        hash: &str,
+        device: &Device,
+        cipher: Option<&Cipher>,
+    ) -> Result<HashMap<String, Tensor>, WTSError> {
+        let on_disk = self.store.get(&object_key(hash))?;
+        let manifest_json = match cipher {
+            Some(cipher) => String::from_utf8(cipher.decrypt(&on_disk, hash.as_bytes())?)
+                .map_err(|e| WTSError::Other(e.to_string()))?,
+            None => String::from_utf8(on_disk).map_err(|e| WTSError::Other(e.to_string()))?,
+        };
+        let manifest: ObjectManifest = serde_json::from_str(&manifest_json)
+            .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+
+        let chunk_store = ChunkStore::new(self.store.as_ref());
+
+        let mut tensors = HashMap::new();
+        for (name, entry) in manifest.tensors {
+            let bytes = chunk_store.get(&entry.chunks, cipher)?;
+            let dtype = parse_dtype(&entry.dtype)?;
+            let tensor = Tensor::from_raw_buffer(&bytes, dtype, &entry.shape, device)
+                .map_err(|e| WTSError::SafeTensorError(e.to_string()))?;
+            tensors.insert(name, tensor);
+        }
+
+        Ok(tensors)
+    }
+
+    /// Collect the chunk hashes reachable from every branch and tag ref,
+    /// then delete any chunk in the store that nothing points to anymore.
+    pub fn gc(&self, passphrase: Option<&str>) -> Result<GcReport, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        let mut reachable = HashSet::new();
+
+        for ref_path in self.all_ref_paths()? {
+            let commit_hash = self.get_reference(&ref_path)?;
+            let iter = CommitIterator {
+                repo: self,
+                current_hash: hex::decode(&commit_hash).ok(),
+                cipher: cipher.clone(),
+            };
+
+            for commit in iter {
+                let commit = commit?;
+                let hex_hash = hex::encode(&commit.hash);
+                let manifest = self.read_object_manifest(&hex_hash, cipher.as_ref())?;
+                for entry in manifest.tensors.values() {
+                    reachable.extend(entry.chunks.iter().cloned());
+                }
+            }
+        }
+
+        let chunk_store = ChunkStore::new(self.store.as_ref());
+        let (chunks_deleted, bytes_freed) = chunk_store.retain(&reachable)?;
+
+        Ok(GcReport {
+            chunks_deleted,
+            bytes_freed,
+        })
+    }
+
+    fn read_object_manifest(
+        &self,
+        hex_hash: &str,
+        cipher: Option<&Cipher>,
+    ) -> Result<ObjectManifest, WTSError> {
+        let on_disk = self.store.get(&object_key(hex_hash))?;
+        let manifest_json = match cipher {
+            Some(cipher) => String::from_utf8(cipher.decrypt(&on_disk, hex_hash.as_bytes())?)
+                .map_err(|e| WTSError::Other(e.to_string()))?,
+            None => String::from_utf8(on_disk).map_err(|e| WTSError::Other(e.to_string()))?,
+        };
+        serde_json::from_str(&manifest_json).map_err(|e| WTSError::SafeTensorError(e.to_string()))
+    }
+
+    fn all_ref_paths(&self) -> Result<Vec<String>, WTSError> {
+        let mut paths = self.store.list("refs/heads")?;
+        paths.extend(self.store.list("refs/tags")?);
+        Ok(paths)
+    }
+}
+
+fn object_key(hash: &str) -> String {
+    format!("objects/{hash}")
+}
+
+fn parse_dtype(s: &str) -> Result<DType, WTSError> {
+    match s {
+        "u8" => Ok(DType::U8),
+        "u32" => Ok(DType::U32),
+        "i64" => Ok(DType::I64),
+        "bf16" => Ok(DType::BF16),
+        "f16" => Ok(DType::F16),
+        "f32" => Ok(DType::F32),
+        "f64" => Ok(DType::F64),
+        other => Err(WTSError::UnsupportedDType(other.to_string())),
     }
 }
 
+/// Walks a commit's ancestry, yielding one fully-decoded [`Commit`] per
+/// hop. Takes an already-derived [`Cipher`] (not a passphrase) so that
+/// traversing a long history only pays the Argon2id KDF cost once, at
+/// construction, rather than once per commit.
 pub struct CommitIterator<'a> {
     repo: &'a Repository,
     current_hash: Option<Vec<u8>>,
+    cipher: Option<Cipher>,
 }
 
 impl<'a> Iterator for CommitIterator<'a> {
@@ -246,7 +590,9 @@ impl<'a> Iterator for CommitIterator<'a> {
             None => None,
             Some(hash) => {
                 let hex_hash = hex::encode(&hash);
-                let commit_result = self.repo.get_commit(&hex_hash);
+                let commit_result = self
+                    .repo
+                    .get_commit_with_cipher(&hex_hash, self.cipher.as_ref());
                 match &commit_result {
                     Ok(commit) => {
                         self.current_hash = commit.parent_hash.clone();
@@ -0,0 +1,254 @@
+//! Commit signing and verification.
+//!
+//! Every signed commit carries the identity of whoever created it and an
+//! Ed25519 signature over the commit's content (hash, parent hash,
+//! timestamp, message, metadata), so published model lineages can be
+//! checked for provenance without trusting the transport they arrived
+//! over.
+
+use candle::Tensor;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::time::UnixTimestamp;
+use crate::{Commit, Repository, WTSError};
+
+/// Who created a commit.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+/// An Ed25519 signature over a commit's canonical bytes, plus the public
+/// key needed to check it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CommitSignature {
+    /// Hex-encoded Ed25519 public key.
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature.
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+struct SignedPayload<'a> {
+    hash: &'a [u8],
+    parent_hash: &'a Option<Vec<u8>>,
+    timestamp: &'a UnixTimestamp,
+    message: &'a str,
+    metadata: &'a serde_json::Value,
+}
+
+fn canonical_bytes(
+    hash: &[u8],
+    parent_hash: &Option<Vec<u8>>,
+    timestamp: &UnixTimestamp,
+    message: &str,
+    metadata: &serde_json::Value,
+) -> Result<Vec<u8>, WTSError> {
+    serde_json::to_vec(&SignedPayload {
+        hash,
+        parent_hash,
+        timestamp,
+        message,
+        metadata,
+    })
+    .map_err(|e| WTSError::SafeTensorError(e.to_string()))
+}
+
+fn sign(signing_key: &SigningKey, payload: &[u8]) -> CommitSignature {
+    let signature: Signature = signing_key.sign(payload);
+    CommitSignature {
+        public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+        signature: hex::encode(signature.to_bytes()),
+    }
+}
+
+fn verify(sig: &CommitSignature, payload: &[u8]) -> Result<bool, WTSError> {
+    let key_bytes = hex::decode(&sig.public_key).map_err(|e| WTSError::Other(e.to_string()))?;
+    let key_bytes: [u8; 32] = key_bytes
+        .try_into()
+        .map_err(|_| WTSError::Other("invalid public key length".to_string()))?;
+    let verifying_key =
+        VerifyingKey::from_bytes(&key_bytes).map_err(|e| WTSError::Other(e.to_string()))?;
+
+    let sig_bytes = hex::decode(&sig.signature).map_err(|e| WTSError::Other(e.to_string()))?;
+    let sig_bytes: [u8; 64] = sig_bytes
+        .try_into()
+        .map_err(|_| WTSError::Other("invalid signature length".to_string()))?;
+    let signature = Signature::from_bytes(&sig_bytes);
+
+    Ok(verifying_key.verify(payload, &signature).is_ok())
+}
+
+/// Load the Ed25519 signing key from `.wts/config` (`signing_key`, a
+/// hex-encoded 32-byte seed) or, failing that, the `WTS_SIGNING_KEY`
+/// environment variable.
+fn load_signing_key(config_signing_key: Option<&str>) -> Result<SigningKey, WTSError> {
+    let hex_key = config_signing_key
+        .map(str::to_string)
+        .or_else(|| std::env::var("WTS_SIGNING_KEY").ok())
+        .ok_or_else(|| {
+            WTSError::Other(
+                "no signing key configured (set .wts/config signing_key or WTS_SIGNING_KEY)"
+                    .to_string(),
+            )
+        })?;
+
+    let bytes = hex::decode(hex_key.trim()).map_err(|e| WTSError::Other(e.to_string()))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| WTSError::Other("signing key must be a 32-byte hex seed".to_string()))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+impl Commit {
+    fn signing_payload(&self) -> Result<Vec<u8>, WTSError> {
+        canonical_bytes(
+            &self.hash,
+            &self.parent_hash,
+            &self.timestamp,
+            &self.message,
+            &self.metadata,
+        )
+    }
+}
+
+impl Repository {
+    /// Like [`Repository::create_commit`], but records `author` and signs
+    /// the commit with the repo's Ed25519 signing key.
+    pub fn create_signed_commit(
+        &self,
+        tensors: &HashMap<String, Tensor>,
+        message: &str,
+        metadata: serde_json::Value,
+        parent: Option<Vec<u8>>,
+        author: Identity,
+        passphrase: Option<&str>,
+    ) -> Result<String, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        let config = self.read_config()?;
+        let signing_key = load_signing_key(config.signing_key.as_deref())?;
+
+        let hash = self.hash_tensors(tensors)?;
+        let hex_hash = hex::encode(&hash);
+        let timestamp = UnixTimestamp(Utc::now());
+
+        let payload = canonical_bytes(&hash, &parent, &timestamp, message, &metadata)?;
+        let signature = sign(&signing_key, &payload);
+
+        let commit = Commit {
+            hash: hash.to_vec(),
+            parent_hash: parent,
+            timestamp,
+            message: message.to_string(),
+            metadata,
+            author: Some(author),
+            signature: Some(signature),
+        };
+
+        let commit_bytes = crate::encode_commit(&commit)?;
+        let on_disk = match &cipher {
+            Some(cipher) => cipher.encrypt(&commit_bytes, hex_hash.as_bytes())?,
+            None => commit_bytes,
+        };
+        self.append_commit_bytes(
+            &hex_hash,
+            commit.parent_hash.clone(),
+            commit.timestamp.clone(),
+            &on_disk,
+        )?;
+
+        self.store_tensors(tensors, &hex_hash, cipher.as_ref())?;
+
+        Ok(hex_hash)
+    }
+
+    /// Check a single commit's signature. Returns `false` (not an error)
+    /// when the commit is unsigned.
+    pub fn verify_commit(&self, hash: &str, passphrase: Option<&str>) -> Result<bool, WTSError> {
+        let commit = self.get_commit_with_passphrase(hash, passphrase)?;
+        match &commit.signature {
+            None => Ok(false),
+            Some(sig) => verify(sig, &commit.signing_payload()?),
+        }
+    }
+
+    /// Walk the commit history starting at `hash` and check every
+    /// commit's signature, returning `(commit_hash, is_valid)` pairs in
+    /// traversal order.
+    pub fn verify_history(
+        &self,
+        hash: &str,
+        passphrase: Option<&str>,
+    ) -> Result<Vec<(String, bool)>, WTSError> {
+        let cipher = self.cipher(passphrase)?;
+        let iter = crate::CommitIterator {
+            repo: self,
+            current_hash: hex::decode(hash).ok(),
+            cipher,
+        };
+
+        let mut results = Vec::new();
+        for commit in iter {
+            let commit = commit?;
+            let hex_hash = hex::encode(&commit.hash);
+            let valid = match &commit.signature {
+                None => false,
+                Some(sig) => verify(sig, &commit.signing_payload()?)?,
+            };
+            results.push((hex_hash, valid));
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_payload(message: &str) -> Vec<u8> {
+        canonical_bytes(
+            &[1, 2, 3, 4],
+            &None,
+            &UnixTimestamp(Utc::now()),
+            message,
+            &json!({"k": "v"}),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sign_then_verify_round_trips() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let payload = test_payload("initial commit");
+
+        let sig = sign(&signing_key, &payload);
+
+        assert!(verify(&sig, &payload).unwrap());
+    }
+
+    #[test]
+    fn verify_rejects_tampered_payload() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let sig = sign(&signing_key, &test_payload("initial commit"));
+
+        let tampered = test_payload("attacker-modified commit");
+
+        assert!(!verify(&sig, &tampered).unwrap());
+    }
+
+    #[test]
+    fn verify_rejects_signature_from_a_different_key() {
+        let payload = test_payload("initial commit");
+        let sig = sign(&SigningKey::from_bytes(&[1u8; 32]), &payload);
+
+        let mut forged = sig.clone();
+        forged.public_key = hex::encode(SigningKey::from_bytes(&[2u8; 32]).verifying_key().to_bytes());
+
+        assert!(!verify(&forged, &payload).unwrap());
+    }
+}
@@ -0,0 +1,293 @@
+//! Pluggable storage backend for commits, tensor objects, chunks, and
+//! refs.
+//!
+//! `Repository` doesn't hard-code filesystem paths for commits, objects,
+//! chunks, or refs; it talks to whatever implements [`ObjectStore`]
+//! instead. Keys are repo-relative strings (`"objects/<hash>"`,
+//! `"objects/chunks/<hash>"`, `"commits/log.bin"`, `"refs/heads/<name>"`,
+//! ...). [`LocalFsStore`] reproduces the original on-disk layout under
+//! `.wts`; [`HttpObjectStore`] talks to a simple per-object REST endpoint
+//! (including S3-compatible gateways), which is the basis for pushing and
+//! fetching a repository's full history — including its tensor chunk
+//! data, the bulk of what a commit actually carries — to a shared
+//! remote. The commit log and its index (see [`crate::index`]) are
+//! themselves stored as single blobs under fixed keys, so they ride along
+//! with everything else.
+
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{Read as _, Seek, SeekFrom, Write as _};
+use std::path::PathBuf;
+
+use crate::WTSError;
+
+/// A content store keyed by repo-relative path strings.
+pub trait ObjectStore: Debug {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), WTSError>;
+    fn get(&self, key: &str) -> Result<Vec<u8>, WTSError>;
+    fn exists(&self, key: &str) -> Result<bool, WTSError>;
+    /// List keys directly under `prefix` (non-recursive). Each returned
+    /// key is a full key of the form `<prefix>/<name>`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, WTSError>;
+    fn delete(&self, key: &str) -> Result<(), WTSError>;
+
+    /// Append `bytes` to `key` (creating it if absent), returning the
+    /// byte offset `bytes` was written at. The default implementation is
+    /// a full read-modify-write over [`get`](ObjectStore::get)/
+    /// [`put`](ObjectStore::put), since that's all this trait guarantees
+    /// in general; backends capable of a true on-disk append (see
+    /// [`LocalFsStore`]) should override it for growing logs.
+    fn append(&self, key: &str, bytes: &[u8]) -> Result<u64, WTSError> {
+        let mut existing = match self.get(key) {
+            Ok(existing) => existing,
+            Err(WTSError::ObjectNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let offset = existing.len() as u64;
+        existing.extend_from_slice(bytes);
+        self.put(key, &existing)?;
+        Ok(offset)
+    }
+
+    /// Read `len` bytes starting at `offset` within `key`. The default
+    /// implementation fetches the whole blob and slices it; backends that
+    /// can seek (see [`LocalFsStore`]) should override it so a single
+    /// record can be read out of a large blob without pulling the whole
+    /// thing into memory.
+    fn get_range(&self, key: &str, offset: u64, len: u32) -> Result<Vec<u8>, WTSError> {
+        let bytes = self.get(key)?;
+        let start = offset as usize;
+        bytes
+            .get(start..start + len as usize)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| WTSError::SafeTensorError(format!("{key}: range out of bounds")))
+    }
+}
+
+/// Default backend: the original `.wts`-relative filesystem layout.
+#[derive(Debug)]
+pub struct LocalFsStore {
+    root: PathBuf,
+}
+
+impl LocalFsStore {
+    /// `root` is the repo's `.wts` directory.
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+impl ObjectStore for LocalFsStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), WTSError> {
+        let path = self.path_for(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, WTSError> {
+        fs::read(self.path_for(key)).map_err(|_| WTSError::ObjectNotFound(key.to_string()))
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, WTSError> {
+        Ok(self.path_for(key).exists())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, WTSError> {
+        let dir = self.path_for(prefix);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if let Some(name) = entry.file_name().to_str() {
+                keys.push(format!("{prefix}/{name}"));
+            }
+        }
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), WTSError> {
+        fs::remove_file(self.path_for(key))?;
+        Ok(())
+    }
+
+    fn append(&self, key: &str, bytes: &[u8]) -> Result<u64, WTSError> {
+        let path = self.path_for(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let offset = file.metadata()?.len();
+        file.write_all(bytes)?;
+        Ok(offset)
+    }
+
+    fn get_range(&self, key: &str, offset: u64, len: u32) -> Result<Vec<u8>, WTSError> {
+        let mut file = fs::File::open(self.path_for(key))
+            .map_err(|_| WTSError::ObjectNotFound(key.to_string()))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; len as usize];
+        file.read_exact(&mut buf)
+            .map_err(|_| WTSError::SafeTensorError(format!("{key}: range out of bounds")))?;
+        Ok(buf)
+    }
+}
+
+/// Talks to a remote object endpoint over plain HTTP verbs: `PUT` to
+/// store, `GET` to fetch, `HEAD` to check existence, `DELETE` to remove,
+/// and `GET {base}?prefix=<prefix>` (one key per line) to list. Works
+/// against any gateway exposing that contract, including S3-compatible
+/// stores fronted by a small REST shim or presigned-URL proxy.
+#[derive(Debug)]
+pub struct HttpObjectStore {
+    base_url: String,
+}
+
+impl HttpObjectStore {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), key)
+    }
+}
+
+impl ObjectStore for HttpObjectStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), WTSError> {
+        ureq::put(&self.url_for(key))
+            .send_bytes(bytes)
+            .map_err(|e| WTSError::Other(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, WTSError> {
+        let response = ureq::get(&self.url_for(key))
+            .call()
+            .map_err(|_| WTSError::ObjectNotFound(key.to_string()))?;
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(WTSError::Io)?;
+        Ok(bytes)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, WTSError> {
+        match ureq::head(&self.url_for(key)).call() {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(404, _)) => Ok(false),
+            Err(e) => Err(WTSError::Other(e.to_string())),
+        }
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, WTSError> {
+        let url = format!("{}?prefix={}", self.base_url.trim_end_matches('/'), prefix);
+        let response = ureq::get(&url)
+            .call()
+            .map_err(|e| WTSError::Other(e.to_string()))?;
+        let text = response
+            .into_string()
+            .map_err(WTSError::Io)?;
+        Ok(text.lines().filter(|l| !l.is_empty()).map(str::to_string).collect())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), WTSError> {
+        ureq::delete(&self.url_for(key))
+            .call()
+            .map_err(|e| WTSError::Other(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_store(name: &str) -> (LocalFsStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wts-store-test-{name}-{}", std::process::id()));
+        (LocalFsStore::new(dir.clone()), dir)
+    }
+
+    #[test]
+    fn put_then_get_round_trips() {
+        let (store, dir) = test_store("roundtrip");
+
+        store.put("objects/deadbeef", b"tensor manifest bytes").unwrap();
+        assert_eq!(store.get("objects/deadbeef").unwrap(), b"tensor manifest bytes");
+        assert!(store.exists("objects/deadbeef").unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn get_on_missing_key_is_object_not_found() {
+        let (store, dir) = test_store("missing");
+
+        assert!(matches!(store.get("objects/nope"), Err(WTSError::ObjectNotFound(_))));
+        assert!(!store.exists("objects/nope").unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn list_returns_keys_under_prefix_without_repeating_it() {
+        let (store, dir) = test_store("list");
+
+        store.put("refs/heads/main", b"aa").unwrap();
+        store.put("refs/heads/dev", b"bb").unwrap();
+
+        let mut keys = store.list("refs/heads").unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["refs/heads/dev".to_string(), "refs/heads/main".to_string()]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn delete_removes_the_key() {
+        let (store, dir) = test_store("delete");
+
+        store.put("objects/aa", b"bytes").unwrap();
+        store.delete("objects/aa").unwrap();
+        assert!(!store.exists("objects/aa").unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn append_extends_an_existing_key_and_reports_the_prior_offset() {
+        let (store, dir) = test_store("append");
+
+        let first_offset = store.append("commits/log.bin", b"hello").unwrap();
+        let second_offset = store.append("commits/log.bin", b"world").unwrap();
+
+        assert_eq!(first_offset, 0);
+        assert_eq!(second_offset, 5);
+        assert_eq!(store.get("commits/log.bin").unwrap(), b"helloworld");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn get_range_reads_a_slice_without_the_whole_blob() {
+        let (store, dir) = test_store("range");
+
+        store.put("commits/log.bin", b"helloworld").unwrap();
+        assert_eq!(store.get_range("commits/log.bin", 5, 5).unwrap(), b"world");
+        assert!(store.get_range("commits/log.bin", 5, 100).is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}